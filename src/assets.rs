@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+/// Generated Rust module exposing each rendered icon as a `pub const: &'static [u8]` PNG byte
+/// constant, nested under `pub mod` blocks that mirror the input directory tree.
+#[derive(Default)]
+pub struct AssetsWriter {
+    root: Module,
+}
+
+impl AssetsWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, relative_path: PathBuf, pixmap: &tiny_skia::Pixmap) -> Result<()> {
+        let png = pixmap
+            .encode_png()
+            .map_err(|e| anyhow::anyhow!("error encoding png for asset module: {}", e))?;
+        self.root.insert(&relative_path, png);
+        Ok(())
+    }
+
+    pub fn flush(&self, out_path: &Path) -> Result<()> {
+        let mut src = String::new();
+        writeln!(src, "// @generated by svg2png --emit-assets. Do not edit by hand.")?;
+        self.root.write(&mut src, 0)?;
+        std::fs::write(out_path, src)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Module {
+    consts: BTreeMap<String, Vec<u8>>,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, path: &Path, png: Vec<u8>) {
+        let mut components: Vec<&str> = path
+            .components()
+            .filter_map(|c| c.as_os_str().to_str())
+            .collect();
+        let leaf = match components.pop() {
+            Some(leaf) => leaf,
+            None => return,
+        };
+        let mut node = self;
+        for dir in components {
+            node = node.children.entry(sanitize(dir)).or_default();
+        }
+        let stem = Path::new(leaf)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(leaf);
+        node.consts.insert(const_name(stem, path), png);
+    }
+
+    fn write(&self, out: &mut String, depth: usize) -> Result<()> {
+        let indent = "    ".repeat(depth);
+        for (name, png) in &self.consts {
+            writeln!(
+                out,
+                "{}pub const {}: &'static [u8] = &{:?};",
+                indent, name, png
+            )?;
+        }
+        for (name, child) in &self.children {
+            writeln!(out, "{}pub mod {} {{", indent, name)?;
+            child.write(out, depth + 1)?;
+            writeln!(out, "{}}}", indent)?;
+        }
+        Ok(())
+    }
+}
+
+// Replaces characters that can't appear in a Rust identifier with `_` and ensures the result
+// doesn't start with a digit.
+fn sanitize(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if out.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+// Hashes in the path the icon was pushed under (relative to the input directory, or the bare
+// file name for `--icon` inputs) so two icons sharing a file stem in different directories
+// don't collide. The absolute path is not part of the hash.
+fn const_name(stem: &str, path: &Path) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    path.hash(&mut hasher);
+    format!(
+        "{}_{:016X}",
+        sanitize(stem).to_uppercase(),
+        hasher.finish()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_replaces_non_identifier_chars() {
+        assert_eq!(sanitize("my-icon.v2"), "my_icon_v2");
+    }
+
+    #[test]
+    fn sanitize_prefixes_a_leading_digit() {
+        assert_eq!(sanitize("123abc"), "_123abc");
+    }
+
+    #[test]
+    fn const_name_differs_for_same_stem_in_different_dirs() {
+        let a = const_name("save", Path::new("/icons/toolbar/save.svg"));
+        let b = const_name("save", Path::new("/icons/status/save.svg"));
+        assert_ne!(a, b);
+        assert!(a.starts_with("SAVE_"));
+    }
+}
@@ -1,5 +1,10 @@
 use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
 use clap::Parser;
@@ -15,73 +20,326 @@ use resvg::usvg_text_layout::TreeTextToPath;
 use tiny_skia::Color;
 use tiny_skia::PixmapPaint;
 use tiny_skia::Transform;
+use tokio::sync::Semaphore;
 use usvg::Options;
 use usvg::ScreenSize;
 
+mod assets;
+mod preview;
+mod theme;
+
+use assets::AssetsWriter;
+
 #[derive(Parser, Debug)]
 struct Opts {
-    #[arg(value_parser)]
-    inputdir: PathBuf,
-    #[arg(value_parser)]
-    outputdir: PathBuf,
+    /// Not required when resolving icons by name via --icon
+    #[arg(value_parser, required_unless_present = "icons")]
+    inputdir: Option<PathBuf>,
+    /// Not required when previewing
+    #[arg(value_parser, required_unless_present = "command")]
+    outputdir: Option<PathBuf>,
+    /// Resolve this icon name from an installed freedesktop icon theme (per --theme) instead
+    /// of reading a directory of SVGs. May be passed multiple times
+    #[arg(long = "icon")]
+    icons: Vec<String>,
+    /// Icon theme to resolve --icon names against
+    #[arg(long, default_value = "hicolor")]
+    theme: String,
+    /// Preferred icon size, in pixels, used to pick the best-matching theme directory
+    #[arg(long, default_value_t = 256)]
+    icon_size: u32,
+    /// How to fit each icon into the output tile
+    #[arg(long, value_enum, default_value = "fixed")]
+    fit: FitMode,
+    /// Derive each icon's background hue from its file stem instead of sampling it randomly
+    #[arg(long)]
+    deterministic: bool,
+    /// Also emit all rendered icons as a single generated Rust module at this path
+    #[arg(long)]
+    emit_assets: Option<PathBuf>,
+    /// Draw a stroked rounded-rectangle frame around the tile at this stroke width, in
+    /// pixels. Off by default
+    #[arg(long)]
+    border: Option<f32>,
+    /// Corner radius of the `--border` frame, in pixels
+    #[arg(long, default_value_t = 12.)]
+    border_radius: f32,
+    /// Distance the `--border` frame is inset from the tile's edges, in pixels
+    #[arg(long, default_value_t = 8.)]
+    border_inset: f32,
+    /// Maximum number of icons to render concurrently; defaults to the available CPU
+    /// parallelism
+    #[arg(long)]
+    jobs: Option<usize>,
+    /// Print per-file and total render timings, in milliseconds
+    #[arg(long)]
+    perf: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Opts {
+    fn border_opts(&self) -> Option<BorderOpts> {
+        self.border.map(|width| BorderOpts {
+            width,
+            radius: self.border_radius,
+            inset: self.border_inset,
+        })
+    }
+
+    fn render_opts(&self) -> RenderOpts {
+        RenderOpts {
+            fit: self.fit,
+            deterministic: self.deterministic,
+            border: self.border_opts(),
+        }
+    }
+}
+
+/// Parameters for the optional rounded-rectangle border frame, see `Opts::border`.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct BorderOpts {
+    width: f32,
+    radius: f32,
+    inset: f32,
+}
+
+/// Per-icon rendering parameters shared by the file-writing and terminal-preview paths.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RenderOpts {
+    pub(crate) fit: FitMode,
+    pub(crate) deterministic: bool,
+    pub(crate) border: Option<BorderOpts>,
+}
+
+/// Batch-level settings for the file-writing render path (`svg2png`).
+struct RenderConfig {
+    render_opts: RenderOpts,
+    emit_assets: Option<PathBuf>,
+    jobs: usize,
+    perf: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Render icons and display them inline in the terminal instead of saving them
+    Preview {
+        /// Maximum preview width, in terminal cells
+        #[arg(long, default_value_t = 80)]
+        width: u32,
+        /// Maximum preview height, in terminal cells
+        #[arg(long, default_value_t = 40)]
+        height: u32,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum FitMode {
+    /// Force every icon into a fixed-size square render box, distorting non-square artwork
+    Fixed,
+    /// Render each icon at its natural size, only shrinking it if it exceeds the render box
+    Contain,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let opts = Opts::parse();
 
-    svg2png(
-        opts.inputdir.canonicalize()?,
-        opts.outputdir.canonicalize()?,
-    )?;
+    let render_opts = opts.render_opts();
+    let (inputs, input_base) = resolve_inputs(&opts)?;
+    match opts.command {
+        Some(Command::Preview { width, height }) => {
+            preview::preview(inputs, render_opts, width, height)
+        }
+        None => {
+            let jobs = opts.jobs.unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            });
+            svg2png(
+                inputs,
+                input_base,
+                opts.outputdir
+                    .ok_or(anyhow::anyhow!("outputdir is required unless previewing"))?
+                    .canonicalize()?,
+                RenderConfig {
+                    render_opts,
+                    emit_assets: opts.emit_assets,
+                    jobs,
+                    perf: opts.perf,
+                },
+            )
+            .await
+        }
+    }
+}
 
-    Ok(())
+// Gathers the list of SVGs to render: either every file in `inputdir`, or the icons named by
+// `--icon`. Also returns the input directory, when there is one, so asset-module paths can be
+// derived relative to it.
+fn resolve_inputs(opts: &Opts) -> Result<(Vec<PathBuf>, Option<PathBuf>)> {
+    if !opts.icons.is_empty() {
+        let inputs = theme::resolve_all(&opts.theme, &opts.icons, opts.icon_size);
+        return Ok((inputs, None));
+    }
+    let input = opts
+        .inputdir
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("inputdir is required unless using --icon"))?
+        .canonicalize()?;
+    let mut inputs = Vec::new();
+    collect_inputs(&input, &mut inputs);
+    Ok((inputs, Some(input)))
+}
+
+// Recursively collects every file under `dir`, so subdirectories populate nested `pub mod`
+// blocks in `--emit-assets` output instead of being handed to `render_icon` as bogus SVGs.
+fn collect_inputs(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|ent| ent.ok().map(|ent| ent.path())) {
+        if path.is_dir() {
+            collect_inputs(&path, out);
+        } else {
+            out.push(path);
+        }
+    }
 }
 
 lazy_static! {
     static ref RE_SVG: Regex = Regex::new(r".*\.svg$").unwrap();
 }
 
-fn svg2png(input: PathBuf, output: PathBuf) -> Result<()> {
-    let opt = usvg::Options::default();
-    let mut fontdb = fontdb::Database::new();
-    fontdb.load_system_fonts();
-
-    let mut rng = rand::thread_rng();
-    let dist: Uniform<f32> = Uniform::new(0., 360.);
-
-    let inputs = fs::read_dir(input)?
-        .into_iter()
-        .filter_map(|ent| ent.ok().map(|ent| ent.path()))
-        .collect::<Vec<_>>();
-    for path in inputs.iter() {
-        let opath = output.join(format!(
-            "{}.png",
-            path.file_stem()
-                .ok_or(anyhow::anyhow!("no file stem"))?
-                .to_owned()
-                .into_string()
-                .map_err(|_| anyhow::anyhow!("bad os string"))?
-        ));
-        match svg2png1(path.clone(), opath, &mut rng, &dist, &opt, &fontdb) {
-            Ok(()) => {}
-            Err(e) => {
+// Computes the scale factor that fits a `w`x`h` box inside a `max_w`x`max_h` box without
+// ever upscaling.
+fn compute_zoom(w: f32, h: f32, max_w: u32, max_h: u32) -> Result<f32> {
+    let zoom = 1.0_f32
+        .min(max_w.max(2) as f32 / w)
+        .min(max_h.max(2) as f32 / h);
+    if zoom <= 0. {
+        return Err(anyhow::anyhow!("invalid zoom {}", zoom));
+    }
+    Ok(zoom)
+}
+
+fn render_output_path(output: &Path, path: &Path) -> Result<PathBuf> {
+    Ok(output.join(format!(
+        "{}.png",
+        path.file_stem()
+            .ok_or(anyhow::anyhow!("no file stem"))?
+            .to_owned()
+            .into_string()
+            .map_err(|_| anyhow::anyhow!("bad os string"))?
+    )))
+}
+
+// Renders `inputs` concurrently, `jobs` at a time, each on its own blocking task since
+// `resvg::render` is CPU-bound. One bad SVG only fails its own file.
+async fn svg2png(
+    inputs: Vec<PathBuf>,
+    input_base: Option<PathBuf>,
+    output: PathBuf,
+    config: RenderConfig,
+) -> Result<()> {
+    let RenderConfig {
+        render_opts,
+        emit_assets,
+        jobs,
+        perf,
+    } = config;
+    let opt = Arc::new(usvg::Options::default());
+    let fontdb = Arc::new({
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_system_fonts();
+        fontdb
+    });
+    let semaphore = Arc::new(Semaphore::new(jobs.max(1)));
+    let mut assets = emit_assets.is_some().then(AssetsWriter::new);
+    let total_start = Instant::now();
+
+    let mut tasks = Vec::with_capacity(inputs.len());
+    for path in inputs {
+        let output = output.clone();
+        let opt = opt.clone();
+        let fontdb = fontdb.clone();
+        let semaphore = semaphore.clone();
+        let path_for_task = path.clone();
+        tasks.push(tokio::spawn(async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("render semaphore is never closed");
+            let start = Instant::now();
+            let render_result = match render_output_path(&output, &path) {
+                Ok(opath) => {
+                    tokio::task::spawn_blocking(move || {
+                        let mut rng = rand::thread_rng();
+                        let dist: Uniform<f32> = Uniform::new(0., 360.);
+                        render_icon(path.clone(), &mut rng, &dist, &opt, &fontdb, render_opts)
+                            .and_then(|bgpixmap| {
+                                bgpixmap.save_png(&opath)?;
+                                Ok(bgpixmap)
+                            })
+                    })
+                    .await
+                }
+                Err(e) => Ok(Err(e)),
+            };
+            (path_for_task, render_result, start.elapsed())
+        }));
+    }
+
+    for task in tasks {
+        let (path, render_result, elapsed) = task.await?;
+        match render_result {
+            Ok(Ok(bgpixmap)) => {
+                if perf {
+                    println!("{}: {}ms", path.display(), elapsed.as_millis());
+                }
+                if let Some(writer) = assets.as_mut() {
+                    let relative = input_base
+                        .as_ref()
+                        .and_then(|base| path.strip_prefix(base).ok())
+                        .map(|p| p.to_path_buf())
+                        .unwrap_or_else(|| PathBuf::from(path.file_name().unwrap_or_default()));
+                    writer.push(relative, &bgpixmap)?;
+                }
+            }
+            Ok(Err(e)) => {
                 eprintln!("error handling {}: {}", path.display(), e);
             }
-        };
+            Err(join_err) => {
+                eprintln!("render task for {} panicked: {}", path.display(), join_err);
+            }
+        }
+    }
+    if perf {
+        println!("total: {}ms", total_start.elapsed().as_millis());
+    }
+    if let (Some(writer), Some(out_path)) = (&assets, &emit_assets) {
+        writer.flush(out_path)?;
     }
     Ok(())
 }
 
-fn svg2png1(
+// Renders a single SVG onto its composited background pixmap. Does not write anything to
+// disk, so both the file-writing and terminal-preview paths can share it.
+pub(crate) fn render_icon(
     path: PathBuf,
-    opath: PathBuf,
     rng: &mut ThreadRng,
     dist: &Uniform<f32>,
     opt: &Options,
     fontdb: &fontdb::Database,
-) -> Result<()> {
-    let svg_data = std::fs::read(path)?;
+    render_opts: RenderOpts,
+) -> Result<tiny_skia::Pixmap> {
+    let RenderOpts {
+        fit,
+        deterministic,
+        border,
+    } = render_opts;
+    let svg_data = std::fs::read(&path)?;
     let mut tree = usvg::Tree::from_data(&svg_data, &opt)?;
     tree.convert_text(&fontdb, opt.keep_named_groups);
 
@@ -99,8 +357,40 @@ fn svg2png1(
     let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height()).unwrap();
     let mut bgpixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height()).unwrap();
 
-    // randomly generate a hue
-    let h = rng.sample(dist);
+    // in Fixed mode the svg is stretched to exactly fill render_size; in Contain mode it
+    // keeps its natural aspect ratio and is only shrunk if it overflows render_size, then
+    // centered inside the full pixmap_size tile
+    let (fit_to, render_transform) = match fit {
+        FitMode::Fixed => (
+            usvg::FitTo::Size(render_size.width(), render_size.height()),
+            tiny_skia::Transform::from_translate(margin as f32, margin as f32),
+        ),
+        FitMode::Contain => {
+            let zoom = compute_zoom(
+                tree.size.width() as f32,
+                tree.size.height() as f32,
+                render_size.width(),
+                render_size.height(),
+            )?;
+            let tx = (size as f32 - tree.size.width() as f32 * zoom) / 2.;
+            let ty = (size as f32 - tree.size.height() as f32 * zoom) / 2.;
+            (
+                usvg::FitTo::Zoom(zoom),
+                tiny_skia::Transform::from_translate(tx, ty),
+            )
+        }
+    };
+
+    // pick a hue: randomly, or deterministically from the icon's name so reruns are stable
+    let h = if deterministic {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.file_stem()
+            .ok_or(anyhow::anyhow!("no file stem"))?
+            .hash(&mut hasher);
+        (hasher.finish() % 360_000) as f32 / 1000.
+    } else {
+        rng.sample(dist)
+    };
     // pastel-ize it a bit
     let hsl = Hsl::new(h, 0.75, 0.75);
     // generate a color sample and render it to background pixmap as full fill
@@ -110,13 +400,8 @@ fn svg2png1(
     bgpixmap.fill(c);
 
     // do the render
-    resvg::render(
-        &tree,
-        usvg::FitTo::Size(render_size.width(), render_size.height()),
-        tiny_skia::Transform::from_translate(margin as f32, margin as f32),
-        pixmap.as_mut(),
-    )
-    .ok_or(anyhow::anyhow!("error rendering svg layer"))?;
+    resvg::render(&tree, fit_to, render_transform, pixmap.as_mut())
+        .ok_or(anyhow::anyhow!("error rendering svg layer"))?;
 
     // composite
     bgpixmap
@@ -130,6 +415,83 @@ fn svg2png1(
         )
         .ok_or(anyhow::anyhow!("error rendering svg layer onto background"))?;
 
-    bgpixmap.save_png(opath)?;
+    if let Some(border) = border {
+        draw_border(&mut bgpixmap, h, border)?;
+    }
+
+    Ok(bgpixmap)
+}
+
+// Strokes a rounded-rectangle frame inset from the tile's edges, in a darker/saturated
+// variant of the tile's background hue.
+fn draw_border(pixmap: &mut tiny_skia::Pixmap, h: f32, border: BorderOpts) -> Result<()> {
+    let hsl = Hsl::new(h, 0.85, 0.35);
+    let color: Srgb = hsl.into_color();
+    let paint_color = Color::from_rgba(color.red, color.green, color.blue, 1.)
+        .ok_or(anyhow::anyhow!("border color create error"))?;
+
+    let x = border.inset;
+    let y = border.inset;
+    let box_w = pixmap.width() as f32 - border.inset * 2.;
+    let box_h = pixmap.height() as f32 - border.inset * 2.;
+    if box_w <= 0. || box_h <= 0. {
+        return Err(anyhow::anyhow!(
+            "border inset {} leaves no room in a {}x{} tile",
+            border.inset,
+            pixmap.width(),
+            pixmap.height()
+        ));
+    }
+    let path = rounded_rect_path(x, y, box_w, box_h, border.radius)
+        .ok_or(anyhow::anyhow!("error building border path"))?;
+
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color(paint_color);
+    paint.anti_alias = true;
+    let stroke = tiny_skia::Stroke {
+        width: border.width,
+        ..Default::default()
+    };
+    pixmap
+        .stroke_path(&path, &paint, &stroke, Transform::identity(), None)
+        .ok_or(anyhow::anyhow!("error stroking border path"))?;
     Ok(())
 }
+
+// Builds a rounded-rectangle path for the given box, clamping the radius so it never
+// exceeds half the box's shorter side.
+fn rounded_rect_path(x: f32, y: f32, w: f32, h: f32, radius: f32) -> Option<tiny_skia::Path> {
+    let r = radius.max(0.).min(w / 2.).min(h / 2.);
+    let mut pb = tiny_skia::PathBuilder::new();
+    pb.move_to(x + r, y);
+    pb.line_to(x + w - r, y);
+    pb.quad_to(x + w, y, x + w, y + r);
+    pb.line_to(x + w, y + h - r);
+    pb.quad_to(x + w, y + h, x + w - r, y + h);
+    pb.line_to(x + r, y + h);
+    pb.quad_to(x, y + h, x, y + h - r);
+    pb.line_to(x, y + r);
+    pb.quad_to(x, y, x + r, y);
+    pb.close();
+    pb.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_zoom_shrinks_oversized_artwork() {
+        assert_eq!(compute_zoom(200., 100., 100, 100).unwrap(), 0.5);
+    }
+
+    #[test]
+    fn compute_zoom_never_upscales() {
+        assert_eq!(compute_zoom(10., 10., 100, 100).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn compute_zoom_rejects_negative_size() {
+        assert!(compute_zoom(-10., 10., 100, 100).is_err());
+    }
+}
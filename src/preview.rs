@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use rand::distributions::Uniform;
+use resvg::usvg_text_layout::fontdb;
+
+use crate::render_icon;
+use crate::RenderOpts;
+
+pub fn preview(
+    inputs: Vec<PathBuf>,
+    render_opts: RenderOpts,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    let opt = usvg::Options::default();
+    let mut fontdb = fontdb::Database::new();
+    fontdb.load_system_fonts();
+
+    let mut rng = rand::thread_rng();
+    let dist: Uniform<f32> = Uniform::new(0., 360.);
+
+    for path in inputs {
+        match render_icon(path.clone(), &mut rng, &dist, &opt, &fontdb, render_opts) {
+            Ok(bgpixmap) => {
+                println!("{}", path.display());
+                let image = to_rgba_image(&bgpixmap);
+                viuer::print(
+                    &image::DynamicImage::ImageRgba8(image),
+                    &viuer::Config {
+                        width: Some(width),
+                        height: Some(height),
+                        ..Default::default()
+                    },
+                )
+                .map_err(|e| anyhow::anyhow!("error previewing {}: {}", path.display(), e))?;
+            }
+            Err(e) => {
+                eprintln!("error handling {}: {}", path.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `tiny_skia::Pixmap` stores premultiplied-alpha RGBA; `image` expects straight alpha, so we
+/// divide each color channel back out by its alpha before handing the buffer over.
+fn to_rgba_image(pixmap: &tiny_skia::Pixmap) -> image::RgbaImage {
+    let mut data = Vec::with_capacity((pixmap.width() * pixmap.height() * 4) as usize);
+    for px in pixmap.pixels() {
+        let a = px.alpha();
+        let (r, g, b) = if a > 0 {
+            (
+                (px.red() as u32 * 255 / a as u32) as u8,
+                (px.green() as u32 * 255 / a as u32) as u8,
+                (px.blue() as u32 * 255 / a as u32) as u8,
+            )
+        } else {
+            (0, 0, 0)
+        };
+        data.extend_from_slice(&[r, g, b, a]);
+    }
+    image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), data)
+        .expect("pixmap byte buffer always matches its own dimensions")
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+// Resolves freedesktop icon *names* (as opposed to file paths) against an installed icon
+// theme, following the Icon Theme Specification:
+// https://specifications.freedesktop.org/icon-theme-spec/icon-theme-spec-latest.html
+pub fn resolve_all(theme: &str, names: &[String], preferred_size: u32) -> Vec<PathBuf> {
+    names
+        .iter()
+        .filter_map(|name| match resolve(theme, name, preferred_size) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("error resolving `{}`: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn resolve(theme: &str, name: &str, preferred_size: u32) -> Result<PathBuf> {
+    let mut queue = vec![theme.to_string()];
+    let mut visited = HashSet::new();
+    let mut best: Option<(u32, PathBuf)> = None;
+
+    while let Some(theme_name) = queue.pop() {
+        if !visited.insert(theme_name.clone()) {
+            continue;
+        }
+        let mut inherits_hicolor = theme_name == "hicolor";
+        for base in theme_base_dirs(&theme_name) {
+            let index = parse_index_theme(&base.join("index.theme")).unwrap_or_default();
+            for (dir, size) in &index.directories {
+                let candidate = base.join(dir).join(format!("{}.svg", name));
+                if !candidate.is_file() {
+                    continue;
+                }
+                let delta = (*size as i64 - preferred_size as i64).unsigned_abs() as u32;
+                if best.as_ref().is_none_or(|(best_delta, _)| delta < *best_delta) {
+                    best = Some((delta, candidate));
+                }
+            }
+            for parent in &index.inherits {
+                if parent == "hicolor" {
+                    inherits_hicolor = true;
+                }
+                queue.push(parent.clone());
+            }
+        }
+        if !inherits_hicolor {
+            queue.push("hicolor".to_string());
+        }
+    }
+
+    if best.is_none() {
+        let candidate = Path::new("/usr/share/pixmaps").join(format!("{}.svg", name));
+        if candidate.is_file() {
+            best = Some((0, candidate));
+        }
+    }
+
+    best.map(|(_, path)| path)
+        .ok_or_else(|| anyhow::anyhow!("no themed icon found for `{}` in theme `{}`", name, theme))
+}
+
+// Each declared subdirectory paired with its nominal pixel size, and the themes it inherits
+// from (searched once this theme is exhausted).
+#[derive(Default)]
+struct ThemeIndex {
+    directories: Vec<(String, u32)>,
+    inherits: Vec<String>,
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string())
+        .split(':')
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn theme_base_dirs(theme: &str) -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("icons").join(theme))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+fn parse_index_theme(path: &Path) -> Result<ThemeIndex> {
+    let contents = fs::read_to_string(path)?;
+    let mut section = String::new();
+    let mut dir_names = Vec::new();
+    let mut inherits = Vec::new();
+    let mut sizes: HashMap<String, u32> = HashMap::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if section == "Icon Theme" {
+            match key {
+                "Directories" => dir_names = split_csv(value),
+                "Inherits" => inherits = split_csv(value),
+                _ => {}
+            }
+        } else if key == "Size" {
+            if let Ok(size) = value.parse() {
+                sizes.insert(section.clone(), size);
+            }
+        }
+    }
+
+    let directories = dir_names
+        .into_iter()
+        .map(|dir| {
+            let size = *sizes.get(&dir).unwrap_or(&48);
+            (dir, size)
+        })
+        .collect();
+    Ok(ThemeIndex {
+        directories,
+        inherits,
+    })
+}
+
+fn split_csv(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_csv_trims_and_drops_empty_entries() {
+        assert_eq!(
+            split_csv(" 16x16, 32x32 ,, 48x48"),
+            vec!["16x16", "32x32", "48x48"]
+        );
+    }
+
+    #[test]
+    fn parse_index_theme_falls_back_to_default_size() {
+        let path = env::temp_dir().join("bootstrap-icon-renderer-test-index.theme");
+        fs::write(
+            &path,
+            "# comment\n\
+             [Icon Theme]\n\
+             Directories=16x16,32x32\n\
+             Inherits=hicolor, gnome\n\
+             garbage line with no equals\n\
+             [16x16]\n\
+             Size=16\n\
+             [32x32]\n\
+             Size=not-a-number\n",
+        )
+        .unwrap();
+
+        let index = parse_index_theme(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(index.inherits, vec!["hicolor", "gnome"]);
+        assert_eq!(
+            index.directories,
+            vec![("16x16".to_string(), 16), ("32x32".to_string(), 48)]
+        );
+    }
+}